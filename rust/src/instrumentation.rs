@@ -0,0 +1,160 @@
+//! Instrumentation-style integration harness, modeled on btc-wire's
+//! `test.rs`: provisions miner/client/reserve wallets and drives a
+//! reproducible sequence of randomized client -> reserve deposits, checking
+//! the RPC-reported detail category on both sides, that no input UTXO is
+//! spent twice across rounds, and that the reserve's balance reconciles
+//! with the sum of deposits end to end.
+
+use bitcoincore_rpc::bitcoin::{Amount, Network, OutPoint};
+use bitcoincore_rpc::bitcoincore_rpc_json::{AddressType, GetTransactionResultDetailCategory};
+use bitcoincore_rpc::{Error as RpcError, RpcApi};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+use crate::{get_wallet, Config};
+
+const MINER_WALLET: &str = "Miner";
+const CLIENT_WALLET: &str = "Client";
+const RESERVE_WALLET: &str = "Reserve";
+
+const INITIAL_MINING_BLOCKS: u64 = 101;
+const CLIENT_FUNDING_BTC: u64 = 10;
+const DEPOSIT_ROUNDS: usize = 5;
+const MIN_DEPOSIT_SATS: u64 = 1_000;
+const MAX_DEPOSIT_SATS: u64 = 50_000_000;
+/// Fixed so a run's sequence of deposit amounts is reproducible.
+const DEPOSIT_SEED: u64 = 0x0005_ca1e_5eed;
+
+/// Provisions the miner/client/reserve wallets (creating them if absent),
+/// funds the miner and client, then drives `DEPOSIT_ROUNDS` randomized
+/// client -> reserve transfers, mining a block after each and asserting
+/// the transaction's detail category and balances reconcile end to end.
+///
+/// Refuses to run against anything other than regtest.
+pub fn run_instrumentation_harness() -> Result<(), RpcError> {
+    let config = Config::from_env()?;
+
+    let mut miner_rpc = config.create_client(MINER_WALLET)?;
+    let mut client_rpc = config.create_client(CLIENT_WALLET)?;
+    let mut reserve_rpc = config.create_client(RESERVE_WALLET)?;
+
+    let network = miner_rpc.raw().get_blockchain_info()?.chain;
+    if network != Network::Regtest {
+        return Err(RpcError::ReturnedError(
+            "instrumentation harness only runs on regtest".into(),
+        ));
+    }
+
+    get_wallet(&mut miner_rpc, MINER_WALLET)?;
+    get_wallet(&mut client_rpc, CLIENT_WALLET)?;
+    get_wallet(&mut reserve_rpc, RESERVE_WALLET)?;
+
+    let miner_address = miner_rpc
+        .raw()
+        .get_new_address(Some("Mining Reward"), Some(AddressType::Bech32))?
+        .require_network(network)
+        .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
+    miner_rpc.generate_to_address(INITIAL_MINING_BLOCKS, &miner_address)?;
+
+    // Fund the client wallet so it has something to deposit.
+    let client_address = client_rpc
+        .raw()
+        .get_new_address(Some("Client funding"), Some(AddressType::Bech32))?
+        .require_network(network)
+        .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
+    miner_rpc.send_to_address(
+        &client_address,
+        Amount::from_int_btc(CLIENT_FUNDING_BTC),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )?;
+    miner_rpc.generate_to_address(1, &miner_address)?;
+
+    let reserve_address = reserve_rpc
+        .raw()
+        .get_new_address(Some("Reserve deposits"), Some(AddressType::Bech32))?
+        .require_network(network)
+        .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
+
+    let mut rng = StdRng::seed_from_u64(DEPOSIT_SEED);
+    let mut spent_outpoints: HashSet<OutPoint> = HashSet::new();
+    let mut deposited_total = Amount::ZERO;
+    let initial_reserve_balance = reserve_rpc.get_wallet_info()?.balance;
+
+    for round in 0..DEPOSIT_ROUNDS {
+        let deposit = Amount::from_sat(rng.gen_range(MIN_DEPOSIT_SATS..=MAX_DEPOSIT_SATS));
+
+        let tx_id = client_rpc.send_to_address(
+            &reserve_address,
+            deposit,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )?;
+        miner_rpc.generate_to_address(1, &miner_address)?;
+
+        let sender_tx = client_rpc.get_transaction(&tx_id, None)?;
+        assert!(
+            sender_tx
+                .details
+                .iter()
+                .any(|detail| detail.category == GetTransactionResultDetailCategory::Send),
+            "round {round}: client side of {tx_id} missing Send category"
+        );
+
+        let receiver_tx = reserve_rpc.get_transaction(&tx_id, None)?;
+        assert!(
+            receiver_tx
+                .details
+                .iter()
+                .any(|detail| detail.category == GetTransactionResultDetailCategory::Receive),
+            "round {round}: reserve side of {tx_id} missing Receive category"
+        );
+
+        // Each deposit's inputs must be UTXOs no earlier round already spent.
+        let raw_tx = client_rpc.get_raw_transaction(&tx_id, None)?;
+        for input in &raw_tx.input {
+            assert!(
+                spent_outpoints.insert(input.previous_output),
+                "round {round}: input {} double-spent across rounds",
+                input.previous_output
+            );
+        }
+
+        deposited_total += deposit;
+        println!("round {round}: deposited {deposit} from client to reserve");
+    }
+
+    let reserve_balance = reserve_rpc.get_wallet_info()?.balance;
+    println!("Reserve balance after {DEPOSIT_ROUNDS} deposits: {reserve_balance}");
+    assert_eq!(
+        reserve_balance - initial_reserve_balance,
+        deposited_total,
+        "reserve balance did not reconcile with the sum of deposits"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drives the full deposit scenario against a live regtest node, so it
+    /// isn't run as part of the default `cargo test`. Needs the same
+    /// `rpc_url`/`user`/`password` environment that `Config::from_env` reads
+    /// for `run_rpc_scenario`. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn instrumentation_harness_reconciles_balances() {
+        run_instrumentation_harness().expect("instrumentation harness");
+    }
+}
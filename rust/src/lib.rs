@@ -1,25 +1,51 @@
+mod fee;
+pub mod instrumentation;
+mod network;
+mod reconnecting_client;
+mod rpc_error;
+
 use bitcoincore_rpc::bitcoin::{Address, Amount, BlockHash, Network, Txid};
 use bitcoincore_rpc::bitcoincore_rpc_json::AddressType;
-use bitcoincore_rpc::json::LoadWalletResult;
-use bitcoincore_rpc::{Auth, Client, Error as RpcError, RpcApi};
+use bitcoincore_rpc::json::{EstimateMode, LoadWalletResult};
+use bitcoincore_rpc::{Auth, Error as RpcError, RpcApi};
 use dotenv as env;
+use fee::FeePolicy;
+use network::guard_against_mainnet;
+use reconnecting_client::ReconnectingClient;
+use rpc_error::{is_rpc_error, RpcErrorCode};
+use rust_decimal::Decimal;
+use serde_json::json;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::Write;
+use std::thread::sleep;
+use std::time::Duration;
 
 const INITIAL_MINING_BLOCKS: u64 = 101;
 const REQUIRED_MINER_BALANCE: f64 = 20.0;
 const TRANSFER_AMOUNT: u64 = 20;
+const SATS_PER_BTC: i64 = 100_000_000;
+const MAX_WARMUP_RETRIES: u32 = 30;
+const WARMUP_RETRY_DELAY: Duration = Duration::from_millis(500);
+const FEE_CONF_TARGET: u16 = 6;
+
+/// Converts a satoshi count to an exact BTC `Decimal`, rather than routing it
+/// through `f64` where precision can be silently lost.
+fn sats_to_decimal(sats: i64) -> Result<Decimal, RpcError> {
+    Decimal::from(sats)
+        .checked_div(Decimal::from(SATS_PER_BTC))
+        .ok_or_else(|| RpcError::ReturnedError("decimal overflow converting satoshis".into()))
+}
 
 #[derive(Debug)]
-struct Config {
+pub(crate) struct Config {
     rpc_url: String,
     rpc_user: String,
     rpc_password: String,
 }
 
 impl Config {
-    fn from_env() -> Result<Self, RpcError> {
+    pub(crate) fn from_env() -> Result<Self, RpcError> {
         Ok(Self {
             rpc_user: env::var("user").map_err(|_| {
                 RpcError::ReturnedError("cannot load username from env file".into())
@@ -32,10 +58,11 @@ impl Config {
         })
     }
 
-    fn create_client(&self, wallet: &str) -> Result<Client, RpcError> {
-        Client::new(
-            format!("{}/wallet/{}", self.rpc_url, wallet).as_str(),
+    pub(crate) fn create_client(&self, wallet: &str) -> Result<ReconnectingClient, RpcError> {
+        ReconnectingClient::new(
+            &self.rpc_url,
             Auth::UserPass(self.rpc_user.clone(), self.rpc_password.clone()),
+            wallet,
         )
     }
 }
@@ -44,12 +71,12 @@ impl Config {
 struct TransactionDetails {
     txid: Txid,
     miner_input_address: Address,
-    miner_input_amount: f64,
+    miner_input_amount: Decimal,
     trader_output_address: Address,
-    trader_output_amount: f64,
+    trader_output_amount: Decimal,
     miner_change_address: Address,
-    miner_change_amount: f64,
-    fee: f64,
+    miner_change_amount: Decimal,
+    fee: Decimal,
     block_height: u64,
     confirmation_block_hash: BlockHash,
 }
@@ -65,12 +92,12 @@ impl TransactionDetails {
     fn new(
         txid: Txid,
         miner_input_address: Address,
-        miner_input_amount: f64,
+        miner_input_amount: Decimal,
         trader_output_address: Address,
-        trader_output_amount: f64,
+        trader_output_amount: Decimal,
         miner_change_address: Address,
-        miner_change_amount: f64,
-        fee: f64,
+        miner_change_amount: Decimal,
+        fee: Decimal,
         block_height: u64,
         confirmation_block_hash: BlockHash,
     ) -> Self {
@@ -90,17 +117,18 @@ impl TransactionDetails {
 
     /// Creates TransactionDetails from RPC clients and transaction data
     fn from_rpc(
-        miner_rpc: &Client,
-        trader_rpc: &Client,
+        miner_rpc: &mut ReconnectingClient,
+        trader_rpc: &mut ReconnectingClient,
         tx_id: Txid,
         miner_input_address: Address,
         trader_output_address: Address,
         confirmation_block_hash: BlockHash,
+        network: Network,
     ) -> Result<Self, RpcError> {
         let (miner_input_amount, fee) = Self::get_miner_details(miner_rpc, tx_id)?;
         let trader_output_amount = Self::get_trader_amount(trader_rpc, tx_id)?;
         let (miner_change_address, miner_change_amount) =
-            Self::get_change_details(miner_rpc, tx_id, &trader_output_address)?;
+            Self::get_change_details(miner_rpc, tx_id, &trader_output_address, network)?;
         let block_height = Self::get_block_height(miner_rpc, confirmation_block_hash)?;
 
         Ok(Self::new(
@@ -117,44 +145,49 @@ impl TransactionDetails {
         ))
     }
 
-    fn get_miner_details(miner_rpc: &Client, tx_id: Txid) -> Result<(f64, f64), RpcError> {
+    fn get_miner_details(
+        miner_rpc: &mut ReconnectingClient,
+        tx_id: Txid,
+    ) -> Result<(Decimal, Decimal), RpcError> {
         let miner_tx = miner_rpc.get_transaction(&tx_id, None)?;
-        let miner_input_amount = f64::abs(
-            miner_tx
-                .details
-                .iter()
-                .map(|detail| detail.amount.to_btc())
-                .sum(),
-        );
-        let fee = miner_tx
+        let miner_input_sats: i64 = miner_tx
+            .details
+            .iter()
+            .map(|detail| detail.amount.to_sat())
+            .sum();
+        let miner_input_amount = sats_to_decimal(miner_input_sats.abs())?;
+        let fee_sats = miner_tx
             .fee
             .ok_or_else(|| RpcError::ReturnedError("No fee found".into()))?
-            .to_btc();
+            .to_sat();
+        let fee = sats_to_decimal(fee_sats.abs())?;
 
         Ok((miner_input_amount, fee))
     }
 
-    fn get_trader_amount(trader_rpc: &Client, tx_id: Txid) -> Result<f64, RpcError> {
+    fn get_trader_amount(trader_rpc: &mut ReconnectingClient, tx_id: Txid) -> Result<Decimal, RpcError> {
         let trader_tx = trader_rpc.get_transaction(&tx_id, None)?;
-        Ok(trader_tx
+        let trader_output_sats: i64 = trader_tx
             .details
             .iter()
-            .map(|detail| detail.amount.to_btc())
-            .sum())
+            .map(|detail| detail.amount.to_sat())
+            .sum();
+        sats_to_decimal(trader_output_sats)
     }
 
     fn get_change_details(
-        miner_rpc: &Client,
+        miner_rpc: &mut ReconnectingClient,
         tx_id: Txid,
         recipient_output_address: &Address,
-    ) -> Result<(Address, f64), RpcError> {
+        network: Network,
+    ) -> Result<(Address, Decimal), RpcError> {
         let raw_tx = miner_rpc.get_raw_transaction(&tx_id, None)?;
 
         let change_output = raw_tx
             .output
             .iter()
             .find(|output| {
-                if let Ok(addr) = Address::from_script(&output.script_pubkey, Network::Regtest) {
+                if let Ok(addr) = Address::from_script(&output.script_pubkey, network) {
                     addr != *recipient_output_address
                 } else {
                     false
@@ -162,15 +195,15 @@ impl TransactionDetails {
             })
             .ok_or_else(|| RpcError::ReturnedError("No change output found".into()))?;
 
-        let change_address = Address::from_script(&change_output.script_pubkey, Network::Regtest)
+        let change_address = Address::from_script(&change_output.script_pubkey, network)
             .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
 
-        let change_amount = change_output.value.to_btc();
+        let change_amount = sats_to_decimal(change_output.value.to_sat() as i64)?;
 
         Ok((change_address, change_amount))
     }
 
-    fn get_block_height(miner_rpc: &Client, block_hash: BlockHash) -> Result<u64, RpcError> {
+    fn get_block_height(miner_rpc: &mut ReconnectingClient, block_hash: BlockHash) -> Result<u64, RpcError> {
         Ok(miner_rpc.get_block_info(&block_hash)?.height as u64)
     }
 
@@ -178,12 +211,12 @@ impl TransactionDetails {
         vec![
             self.txid.to_string(),
             self.miner_input_address.to_string(),
-            self.miner_input_amount.to_string(),
+            format!("{:.8}", self.miner_input_amount),
             self.trader_output_address.to_string(),
-            self.trader_output_amount.to_string(),
+            format!("{:.8}", self.trader_output_amount),
             self.miner_change_address.to_string(),
-            self.miner_change_amount.to_string(),
-            self.fee.to_string(),
+            format!("{:.8}", self.miner_change_amount),
+            format!("{:.8}", self.fee),
             self.block_height.to_string(),
             self.confirmation_block_hash.to_string(),
         ]
@@ -194,21 +227,27 @@ pub fn run_rpc_scenario() -> Result<(), RpcError> {
     let config = Config::from_env()?;
 
     // Connect to Bitcoin Core RPC
-    let miner_rpc = config.create_client("Miner")?;
-    let trader_rpc = config.create_client("Trader")?;
+    let mut miner_rpc = config.create_client("Miner")?;
+    let mut trader_rpc = config.create_client("Trader")?;
 
     // Get blockchain info
-    let blockchain_info = miner_rpc.get_blockchain_info()?;
+    let blockchain_info = miner_rpc.raw().get_blockchain_info()?;
     println!("Blockchain Info: {blockchain_info:?}");
 
+    // Detect which chain the node is actually running and refuse to run this
+    // destructive mining/spending scenario against mainnet.
+    let network = blockchain_info.chain;
+    guard_against_mainnet(network)?;
+
     // Create/Load the wallets, named 'Miner' and 'Trader'. Have logic to optionally create/load them if they do not exist or not loaded already.
-    get_wallet(&miner_rpc, "Miner")?;
-    get_wallet(&trader_rpc, "Trader")?;
+    get_wallet(&mut miner_rpc, "Miner")?;
+    get_wallet(&mut trader_rpc, "Trader")?;
 
     // Generate spendable balances in the Miner wallet
     let miner_input_address = miner_rpc
+        .raw()
         .get_new_address(Some("Mining Reward"), Some(AddressType::Bech32))?
-        .require_network(Network::Regtest)
+        .require_network(network)
         .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
 
     // generate initial blocks to obtain the funds
@@ -223,24 +262,33 @@ pub fn run_rpc_scenario() -> Result<(), RpcError> {
 
     // Load Trader wallet and generate a new address
     let trader_output_address = trader_rpc
+        .raw()
         .get_new_address(Some("BTC trades"), Some(AddressType::Bech32))?
-        .require_network(Network::Regtest)
+        .require_network(network)
         .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
 
-    // Send BTC from Miner to Trader
+    // Ask the node what it would recommend before we broadcast, so the
+    // scenario can sanity-check the fee it actually pays against this.
+    let estimated_fee_rate = log_estimated_fee_rate(&miner_rpc, FEE_CONF_TARGET)?;
+
+    // Send BTC from Miner to Trader, targeting confirmation within
+    // FEE_CONF_TARGET blocks instead of leaving fee selection to chance.
+    let fee_policy = FeePolicy::ConfTarget(FEE_CONF_TARGET, EstimateMode::Conservative);
+    let fee_args = fee_policy.send_args(Some(false));
     let tx_id = miner_rpc.send_to_address(
         &trader_output_address,
         Amount::from_int_btc(TRANSFER_AMOUNT),
         Some("I will send you some BTC for trading!"),
         Some("my friend best trader"),
         None,
-        None,
-        None,
-        None,
+        fee_args.replaceable,
+        fee_args.conf_target.map(u32::from),
+        fee_args.estimate_mode,
     )?;
 
     // Check transaction in mempool
     let mempool_entry = miner_rpc
+        .raw()
         .get_mempool_entry(&tx_id)
         .map_err(|e| RpcError::ReturnedError(e.to_string()))?;
     println!("Mempool Entry: {mempool_entry:?}");
@@ -249,14 +297,29 @@ pub fn run_rpc_scenario() -> Result<(), RpcError> {
     let confirmation_block = miner_rpc.generate_to_address(1, &miner_input_address)?;
 
     let transaction_details = TransactionDetails::from_rpc(
-        &miner_rpc,
-        &trader_rpc,
+        &mut miner_rpc,
+        &mut trader_rpc,
         tx_id,
         miner_input_address,
         trader_output_address,
         *confirmation_block.first().unwrap(),
+        network,
     )?;
 
+    // Sanity-check the fee we actually paid against the node's own
+    // recommendation (loosely — regtest fee estimation can return no data,
+    // and confirmation can land a block or two sooner than targeted).
+    if let Some(feerate_btc_per_kvb) = estimated_fee_rate {
+        let vsize = miner_rpc.raw().get_raw_transaction_info(&tx_id, None)?.vsize;
+        let feerate: Decimal = format!("{feerate_btc_per_kvb:.8}").parse().unwrap_or_default();
+        let expected_fee = feerate * Decimal::from(vsize as u64) / Decimal::from(1000);
+        assert!(
+            transaction_details.fee <= expected_fee * Decimal::from(5),
+            "miner tx fee {} BTC far exceeds the {feerate_btc_per_kvb} BTC/kvB estimate for a {vsize} vB tx",
+            transaction_details.fee,
+        );
+    }
+
     // Write the data to ../out.txt
     println!("===");
     println!("Saving result:\n{transaction_details}");
@@ -265,6 +328,26 @@ pub fn run_rpc_scenario() -> Result<(), RpcError> {
     Ok(())
 }
 
+/// Logs the node's `estimatesmartfee` recommendation for `conf_target`,
+/// returning the estimated rate in BTC/kvB if the node was able to produce
+/// one. Uses the generic `call` interface since `estimatesmartfee` has no
+/// typed wrapper in `RpcApi`.
+fn log_estimated_fee_rate(
+    rpc: &ReconnectingClient,
+    conf_target: u16,
+) -> Result<Option<f64>, RpcError> {
+    #[derive(serde::Deserialize)]
+    struct EstimateSmartFeeResult {
+        feerate: Option<f64>,
+    }
+
+    let estimate = rpc
+        .raw()
+        .call::<EstimateSmartFeeResult>("estimatesmartfee", &[json!(conf_target)])?;
+    println!("Estimated fee rate for {conf_target} blocks: {:?} BTC/kvB", estimate.feerate);
+    Ok(estimate.feerate)
+}
+
 fn write_to_file(details: &TransactionDetails) -> Result<(), RpcError> {
     let mut file = File::create("../out.txt")?;
     for line in details.to_lines() {
@@ -273,7 +356,25 @@ fn write_to_file(details: &TransactionDetails) -> Result<(), RpcError> {
     Ok(())
 }
 
-fn get_wallet(rpc: &Client, wallet_name: &str) -> bitcoincore_rpc::Result<LoadWalletResult> {
+pub(crate) fn get_wallet(
+    rpc: &mut ReconnectingClient,
+    wallet_name: &str,
+) -> bitcoincore_rpc::Result<LoadWalletResult> {
+    for _ in 0..MAX_WARMUP_RETRIES {
+        match try_get_wallet(rpc, wallet_name) {
+            Err(e) if is_rpc_error(&e, RpcErrorCode::InWarmup) => sleep(WARMUP_RETRY_DELAY),
+            result => return result,
+        }
+    }
+    Err(RpcError::ReturnedError(
+        "node stayed in warmup too long while loading wallet".into(),
+    ))
+}
+
+fn try_get_wallet(
+    rpc: &mut ReconnectingClient,
+    wallet_name: &str,
+) -> bitcoincore_rpc::Result<LoadWalletResult> {
     // Check if wallet exists
     let wallets = rpc.list_wallets()?;
     let wallet_exists = wallets.iter().any(|wallet| wallet == wallet_name);
@@ -283,10 +384,18 @@ fn get_wallet(rpc: &Client, wallet_name: &str) -> bitcoincore_rpc::Result<LoadWa
         match rpc.load_wallet(wallet_name) {
             Ok(result) => Ok(result),
             Err(e) => {
-                // If error is "already loaded" (code -4), unload and retry
-                if e.to_string().contains("code: -4") {
-                    rpc.unload_wallet(Some(wallet_name))?;
+                // Already loaded: unload and retry
+                if is_rpc_error(&e, RpcErrorCode::WalletAlreadyLoaded) {
+                    rpc.raw().unload_wallet(Some(wallet_name))?;
                     rpc.load_wallet(wallet_name)
+                } else if is_rpc_error(&e, RpcErrorCode::WalletNotFound) {
+                    Err(RpcError::ReturnedError(format!(
+                        "wallet '{wallet_name}' vanished between list_wallets and load_wallet: {e}"
+                    )))
+                } else if is_rpc_error(&e, RpcErrorCode::WalletError) {
+                    Err(RpcError::ReturnedError(format!(
+                        "wallet error loading '{wallet_name}': {e}"
+                    )))
                 } else {
                     Err(e)
                 }
@@ -294,10 +403,15 @@ fn get_wallet(rpc: &Client, wallet_name: &str) -> bitcoincore_rpc::Result<LoadWa
         }
     } else {
         // Try creating a new wallet
-        rpc.create_wallet(wallet_name, None, None, None, None)
+        rpc.raw()
+            .create_wallet(wallet_name, None, None, None, None)
             .map_err(|e| {
-                if e.to_string().contains("code: -4") {
+                if is_rpc_error(&e, RpcErrorCode::WalletAlreadyExists) {
                     RpcError::ReturnedError("Wallet already exists but was not listed".into())
+                } else if is_rpc_error(&e, RpcErrorCode::WalletError) {
+                    RpcError::ReturnedError(format!(
+                        "wallet error creating '{wallet_name}': {e}"
+                    ))
                 } else {
                     e
                 }
@@ -0,0 +1,12 @@
+use bitcoincore_rpc::bitcoin::Network;
+use bitcoincore_rpc::Error as RpcError;
+
+/// Refuses to run the destructive mining/spending scenario against mainnet.
+pub(crate) fn guard_against_mainnet(network: Network) -> Result<(), RpcError> {
+    if network == Network::Bitcoin {
+        return Err(RpcError::ReturnedError(
+            "refusing to run scenario on mainnet".into(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use bitcoincore_rpc::bitcoin::{Address, Amount, BlockHash, Transaction, Txid};
+use bitcoincore_rpc::json::{
+    EstimateMode, GetBlockResult, GetTransactionResult, GetWalletInfoResult, LoadWalletResult,
+};
+use bitcoincore_rpc::jsonrpc::Error as JsonRpcError;
+use bitcoincore_rpc::{Auth, Client, Error as RpcError, RpcApi};
+use std::thread::sleep;
+use std::time::Duration;
+
+const MAX_RETRIES: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Wraps a `bitcoincore_rpc::Client` and transparently reconnects when the
+/// node restarts or the localhost socket drops mid-scenario, instead of
+/// failing the whole run on a connection hiccup.
+///
+/// Only connection-level failures are retried; JSON-RPC application errors
+/// (bad arguments, wallet errors, etc.) are returned to the caller
+/// immediately so a genuinely bad request doesn't loop forever.
+pub(crate) struct ReconnectingClient {
+    rpc_url: String,
+    auth: Auth,
+    wallet: String,
+    inner: Client,
+}
+
+impl ReconnectingClient {
+    pub(crate) fn new(rpc_url: &str, auth: Auth, wallet: &str) -> Result<Self, RpcError> {
+        let inner = Self::connect(rpc_url, &auth, wallet)?;
+        Ok(Self {
+            rpc_url: rpc_url.to_owned(),
+            auth,
+            wallet: wallet.to_owned(),
+            inner,
+        })
+    }
+
+    fn connect(rpc_url: &str, auth: &Auth, wallet: &str) -> Result<Client, RpcError> {
+        Client::new(format!("{rpc_url}/wallet/{wallet}").as_str(), auth.clone())
+    }
+
+    /// Gives direct access to the wrapped client for RPC calls this scenario
+    /// only ever makes once (e.g. during setup), where a transparent retry
+    /// isn't worth the extra surface.
+    pub(crate) fn raw(&self) -> &Client {
+        &self.inner
+    }
+
+    /// True when `error` indicates a dropped connection or transport failure
+    /// rather than a JSON-RPC application error.
+    fn is_retryable(error: &RpcError) -> bool {
+        matches!(
+            error,
+            RpcError::Io(_) | RpcError::JsonRpc(JsonRpcError::Transport(_))
+        )
+    }
+
+    fn with_retry<T>(
+        &mut self,
+        mut call: impl FnMut(&Client) -> Result<T, RpcError>,
+    ) -> Result<T, RpcError> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match call(&self.inner) {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt < MAX_RETRIES && Self::is_retryable(&error) => {
+                    sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                    self.inner = Self::connect(&self.rpc_url, &self.auth, &self.wallet)?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    pub(crate) fn get_transaction(
+        &mut self,
+        txid: &Txid,
+        include_watchonly: Option<bool>,
+    ) -> Result<GetTransactionResult, RpcError> {
+        self.with_retry(|client| client.get_transaction(txid, include_watchonly))
+    }
+
+    pub(crate) fn get_raw_transaction(
+        &mut self,
+        txid: &Txid,
+        block_hash: Option<&BlockHash>,
+    ) -> Result<Transaction, RpcError> {
+        self.with_retry(|client| client.get_raw_transaction(txid, block_hash))
+    }
+
+    pub(crate) fn generate_to_address(
+        &mut self,
+        nblocks: u64,
+        address: &Address,
+    ) -> Result<Vec<BlockHash>, RpcError> {
+        self.with_retry(|client| client.generate_to_address(nblocks, address))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_to_address(
+        &mut self,
+        address: &Address,
+        amount: Amount,
+        comment: Option<&str>,
+        comment_to: Option<&str>,
+        subtract_fee: Option<bool>,
+        replaceable: Option<bool>,
+        confirmation_target: Option<u32>,
+        estimate_mode: Option<EstimateMode>,
+    ) -> Result<Txid, RpcError> {
+        self.with_retry(|client| {
+            client.send_to_address(
+                address,
+                amount,
+                comment,
+                comment_to,
+                subtract_fee,
+                replaceable,
+                confirmation_target,
+                estimate_mode,
+            )
+        })
+    }
+
+    pub(crate) fn get_block_info(&mut self, block_hash: &BlockHash) -> Result<GetBlockResult, RpcError> {
+        self.with_retry(|client| client.get_block_info(block_hash))
+    }
+
+    pub(crate) fn get_wallet_info(&mut self) -> Result<GetWalletInfoResult, RpcError> {
+        self.with_retry(|client| client.get_wallet_info())
+    }
+
+    pub(crate) fn list_wallets(&mut self) -> Result<Vec<String>, RpcError> {
+        self.with_retry(|client| client.list_wallets())
+    }
+
+    pub(crate) fn load_wallet(&mut self, wallet: &str) -> Result<LoadWalletResult, RpcError> {
+        self.with_retry(|client| client.load_wallet(wallet))
+    }
+}
@@ -0,0 +1,46 @@
+use bitcoincore_rpc::jsonrpc::Error as JsonRpcError;
+use bitcoincore_rpc::Error as RpcError;
+
+/// Named bitcoind JSON-RPC error codes this scenario cares about, so wallet
+/// load/create/unload logic can branch on intent instead of string-matching
+/// `"code: -N"` out of the error's `Display` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RpcErrorCode {
+    /// Generic wallet error (`RPC_WALLET_ERROR`), distinct from the other
+    /// variants that also wire to -4 so a caller can still report "some
+    /// other wallet error" instead of falling through as unrecognized.
+    WalletError,
+    WalletNotFound,
+    WalletAlreadyLoaded,
+    /// `createwallet` also reports `RPC_WALLET_ERROR` (-4) when a wallet
+    /// with that name already exists on disk — same wire code as
+    /// `WalletError`, disambiguated by call site rather than by value.
+    WalletAlreadyExists,
+    InWarmup,
+}
+
+impl RpcErrorCode {
+    fn code(self) -> i32 {
+        match self {
+            RpcErrorCode::WalletError => -4,
+            RpcErrorCode::WalletNotFound => -18,
+            RpcErrorCode::WalletAlreadyLoaded => -35,
+            RpcErrorCode::WalletAlreadyExists => -4,
+            RpcErrorCode::InWarmup => -28,
+        }
+    }
+}
+
+/// Extracts the numeric JSON-RPC error code from a `bitcoincore_rpc::Error`,
+/// if it carries one, without relying on its `Display` formatting.
+pub(crate) fn error_code(error: &RpcError) -> Option<i32> {
+    match error {
+        RpcError::JsonRpc(JsonRpcError::Rpc(e)) => Some(e.code),
+        _ => None,
+    }
+}
+
+/// True when `error`'s JSON-RPC code matches `code`.
+pub(crate) fn is_rpc_error(error: &RpcError, code: RpcErrorCode) -> bool {
+    error_code(error) == Some(code.code())
+}